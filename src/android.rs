@@ -1,10 +1,10 @@
-use crate::{Error, Product, ProductType, PurchaseResult, Result, SubscriptionPeriod, SubscriptionStatus};
+use crate::{pending, Error, Product, PurchaseResult, Result, SubscriptionStatus};
 use jni::objects::{JObject, JString, JValue};
-use jni::sys::{jlong, jobject};
+use jni::sys::{jboolean, jlong, jobject};
 use jni::JNIEnv;
 use once_cell::sync::OnceCell;
 use tauri::AppHandle;
-use std::sync::{Arc, Mutex};
+use std::ffi::CString;
 
 static SUBSCRIPTIONS_INSTANCE: OnceCell<jobject> = OnceCell::new();
 
@@ -16,48 +16,68 @@ pub fn init_android(app_handle: &AppHandle) -> Result<()> {
     })?;
 
     let env = unsafe { jni::JavaVM::from_raw(activity as *mut _)? }.attach_current_thread()?;
-    
+
     // Create the Subscriptions singleton instance
     let subscriptions_class = env.find_class("com/tauri/subscriptions/Subscriptions")?;
     let method_id = env.get_static_method_id(subscriptions_class, "getInstance", "(Landroid/content/Context;)Lcom/tauri/subscriptions/Subscriptions;")?;
-    
+
     // Call Subscriptions.getInstance(activity)
     let instance = env.call_static_method_object(
         subscriptions_class,
         method_id,
         &[JValue::Object(JObject::from_raw(activity as jobject))]
     )?;
-    
+
     // Save the instance for later use
     SUBSCRIPTIONS_INSTANCE.set(instance.into_raw()).map_err(|_| {
         Error::PlatformError("Failed to initialize Subscriptions".to_string())
     })?;
-    
+
     Ok(())
 }
 
+/// Called from the Java callback once the Billing Library has a result (or
+/// error) for `request_id`, forwarding into the shared pending registry.
+///
+/// # Safety
+/// `payload` must be a valid JNI string reference for the duration of this
+/// call.
+#[no_mangle]
+pub unsafe extern "system" fn Java_com_tauri_subscriptions_Subscriptions_onNativeResult(
+    env: JNIEnv,
+    _class: JObject,
+    request_id: jlong,
+    success: jboolean,
+    payload: JString,
+) {
+    let payload_str: String = env
+        .get_string(payload)
+        .map(|s| s.into())
+        .unwrap_or_default();
+    if let Ok(c_payload) = CString::new(payload_str) {
+        pending::on_native_result(request_id as u64, success != 0, c_payload.as_ptr());
+    }
+}
+
 pub async fn get_products_android(app_handle: AppHandle, product_ids: Vec<String>) -> Result<Vec<Product>> {
-    let result = Arc::new(Mutex::new(None));
-    let result_clone = result.clone();
-    
     // Get the Android Activity
     let activity = app_handle.config().plugin_init_hook(|app_handle| {
         let activity = app_handle.get_activity()?;
         Ok(activity as jlong)
     })?;
-    
+
     let env = unsafe { jni::JavaVM::from_raw(activity as *mut _)? }.attach_current_thread()?;
-    
+
     // Get the Subscriptions instance
     let subscriptions = match SUBSCRIPTIONS_INSTANCE.get() {
         Some(instance) => JObject::from_raw(*instance),
         None => return Err(Error::PlatformError("Subscriptions not initialized".to_string())),
     };
-    
+
     // Convert product_ids to Java ArrayList
     let array_list_class = env.find_class("java/util/ArrayList")?;
     let array_list = env.new_object(array_list_class, "()V", &[])?;
-    
+
     for id in product_ids.iter() {
         let j_id = env.new_string(id)?;
         env.call_method(
@@ -67,33 +87,20 @@ pub async fn get_products_android(app_handle: AppHandle, product_ids: Vec<String
             &[JValue::Object(j_id)]
         )?;
     }
-    
-    // Call getProducts method
-    // In a real implementation, we would define a callback that populates our result
-    // For this example, we'll mock the results
-    
-    // Mock implementation
-    let mut mock_products = Vec::new();
-    for id in product_ids.iter() {
-        mock_products.push(Product {
-            id: id.clone(),
-            title: format!("Product {}", id),
-            description: "Description".to_string(),
-            price: "$9.99".to_string(),
-            price_amount: 9.99,
-            currency_code: "USD".to_string(),
-            product_type: ProductType::Subscription,
-            subscription_period: Some(SubscriptionPeriod::Month),
-            subscription_period_unit: Some(1),
-        });
-    }
-    
-    *result.lock().unwrap() = Some(mock_products);
-    
-    match Arc::try_unwrap(result).unwrap().into_inner().unwrap() {
-        Some(products) => Ok(products),
-        None => Err(Error::ProductRetrievalError("Failed to retrieve products".to_string())),
-    }
+
+    // Call getProducts, passing a request id that the Billing Library
+    // callback hands back to `Java_..._onNativeResult` once it resolves.
+    let (request_id, rx) = pending::register();
+    env.call_method(
+        subscriptions,
+        "getProducts",
+        "(Ljava/util/List;J)V",
+        &[JValue::Object(array_list), JValue::Long(request_id as i64)],
+    )?;
+
+    let payload = pending::wait(request_id, rx, || {}).await?;
+    serde_json::from_value(payload)
+        .map_err(|e| Error::ProductRetrievalError(format!("invalid product payload: {e}")))
 }
 
 pub async fn purchase_product_android(app_handle: AppHandle, product_id: String) -> Result<PurchaseResult> {
@@ -102,35 +109,70 @@ pub async fn purchase_product_android(app_handle: AppHandle, product_id: String)
         let activity = app_handle.get_activity()?;
         Ok(activity as jlong)
     })?;
-    
+
     let env = unsafe { jni::JavaVM::from_raw(activity as *mut _)? }.attach_current_thread()?;
-    
+
     // Get the Subscriptions instance
     let subscriptions = match SUBSCRIPTIONS_INSTANCE.get() {
         Some(instance) => JObject::from_raw(*instance),
         None => return Err(Error::PlatformError("Subscriptions not initialized".to_string())),
     };
-    
-    // In a real implementation, we would call purchaseProductById method
-    // and use a callback to get the result
-    
-    // Mock successful purchase
-    Ok(PurchaseResult {
-        product_id,
-        transaction_id: format!("android_transaction_{}", rand::random::<u64>()),
-        purchase_time: std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs(),
-        is_acknowledged: true,
-        subscription_expiry_time: Some(
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs() + 30 * 24 * 60 * 60 // 30 days
-        ),
-        receipt_data: Some("sample_receipt_data".to_string()),
-    })
+
+    // Call purchaseProductById, resolving `request_id` from the
+    // PurchasesUpdatedListener callback once Billing reports the outcome.
+    let (request_id, rx) = pending::register();
+    let j_product_id = env.new_string(&product_id)?;
+    env.call_method(
+        subscriptions,
+        "purchaseProductById",
+        "(Ljava/lang/String;J)V",
+        &[JValue::Object(j_product_id), JValue::Long(request_id as i64)],
+    )?;
+
+    let payload = pending::wait(request_id, rx, || {}).await?;
+    serde_json::from_value(payload)
+        .map_err(|e| Error::PurchaseError(format!("invalid purchase payload: {e}")))
+}
+
+pub async fn purchase_product_with_offer_android(
+    app_handle: AppHandle,
+    product_id: String,
+    offer_id: String,
+) -> Result<PurchaseResult> {
+    // Get the Android Activity
+    let activity = app_handle.config().plugin_init_hook(|app_handle| {
+        let activity = app_handle.get_activity()?;
+        Ok(activity as jlong)
+    })?;
+
+    let env = unsafe { jni::JavaVM::from_raw(activity as *mut _)? }.attach_current_thread()?;
+
+    // Get the Subscriptions instance
+    let subscriptions = match SUBSCRIPTIONS_INSTANCE.get() {
+        Some(instance) => JObject::from_raw(*instance),
+        None => return Err(Error::PlatformError("Subscriptions not initialized".to_string())),
+    };
+
+    // Call purchaseProductWithOffer with `offer_id` (an offer from
+    // `subscriptionOfferDetails`), resolving `request_id` from the
+    // PurchasesUpdatedListener callback once Billing reports the outcome.
+    let (request_id, rx) = pending::register();
+    let j_product_id = env.new_string(&product_id)?;
+    let j_offer_id = env.new_string(&offer_id)?;
+    env.call_method(
+        subscriptions,
+        "purchaseProductWithOffer",
+        "(Ljava/lang/String;Ljava/lang/String;J)V",
+        &[
+            JValue::Object(j_product_id),
+            JValue::Object(j_offer_id),
+            JValue::Long(request_id as i64),
+        ],
+    )?;
+
+    let payload = pending::wait(request_id, rx, || {}).await?;
+    serde_json::from_value(payload)
+        .map_err(|e| Error::PurchaseError(format!("invalid purchase payload: {e}")))
 }
 
 pub async fn restore_purchases_android(app_handle: AppHandle) -> Result<Vec<PurchaseResult>> {
@@ -139,37 +181,28 @@ pub async fn restore_purchases_android(app_handle: AppHandle) -> Result<Vec<Purc
         let activity = app_handle.get_activity()?;
         Ok(activity as jlong)
     })?;
-    
+
     let env = unsafe { jni::JavaVM::from_raw(activity as *mut _)? }.attach_current_thread()?;
-    
+
     // Get the Subscriptions instance
     let subscriptions = match SUBSCRIPTIONS_INSTANCE.get() {
         Some(instance) => JObject::from_raw(*instance),
         None => return Err(Error::PlatformError("Subscriptions not initialized".to_string())),
     };
-    
-    // In a real implementation, we would call restorePurchases method
-    // and use a callback to get the result
-    
-    // Mock restored purchases
-    Ok(vec![
-        PurchaseResult {
-            product_id: "com.example.subscription.monthly".to_string(),
-            transaction_id: format!("android_transaction_{}", rand::random::<u64>()),
-            purchase_time: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs() - 15 * 24 * 60 * 60, // 15 days ago
-            is_acknowledged: true,
-            subscription_expiry_time: Some(
-                std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs() + 15 * 24 * 60 * 60 // 15 days remaining
-            ),
-            receipt_data: Some("sample_receipt_data".to_string()),
-        }
-    ])
+
+    // Call restorePurchases, resolving `request_id` once Billing's
+    // queryPurchasesAsync callback returns.
+    let (request_id, rx) = pending::register();
+    env.call_method(
+        subscriptions,
+        "restorePurchases",
+        "(J)V",
+        &[JValue::Long(request_id as i64)],
+    )?;
+
+    let payload = pending::wait(request_id, rx, || {}).await?;
+    serde_json::from_value(payload)
+        .map_err(|e| Error::PlatformError(format!("invalid restore payload: {e}")))
 }
 
 pub async fn get_subscription_status_android(app_handle: AppHandle, product_id: String) -> Result<SubscriptionStatus> {
@@ -178,30 +211,27 @@ pub async fn get_subscription_status_android(app_handle: AppHandle, product_id:
         let activity = app_handle.get_activity()?;
         Ok(activity as jlong)
     })?;
-    
+
     let env = unsafe { jni::JavaVM::from_raw(activity as *mut _)? }.attach_current_thread()?;
-    
+
     // Get the Subscriptions instance
     let subscriptions = match SUBSCRIPTIONS_INSTANCE.get() {
         Some(instance) => JObject::from_raw(*instance),
         None => return Err(Error::PlatformError("Subscriptions not initialized".to_string())),
     };
-    
-    // In a real implementation, we would call getSubscriptionStatus method
-    // and parse the result
-    
-    // Mock subscription status
-    Ok(SubscriptionStatus {
-        product_id,
-        is_active: true,
-        expiry_date: Some(
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs() + 15 * 24 * 60 * 60 // 15 days remaining
-        ),
-        auto_renew_status: true,
-        is_in_trial_period: false,
-        is_in_grace_period: false,
-    })
-}
\ No newline at end of file
+
+    // Call getSubscriptionStatus, resolving `request_id` once Billing's
+    // callback reports the current purchase state.
+    let (request_id, rx) = pending::register();
+    let j_product_id = env.new_string(&product_id)?;
+    env.call_method(
+        subscriptions,
+        "getSubscriptionStatus",
+        "(Ljava/lang/String;J)V",
+        &[JValue::Object(j_product_id), JValue::Long(request_id as i64)],
+    )?;
+
+    let payload = pending::wait(request_id, rx, || {}).await?;
+    serde_json::from_value(payload)
+        .map_err(|e| Error::SubscriptionError(format!("invalid status payload: {e}")))
+}
@@ -0,0 +1,360 @@
+//! Push- and poll-driven subscription lifecycle events.
+//!
+//! `get_subscription_status` is pull-based, so apps miss renewals,
+//! cancellations, refunds, and revocations that happen while they're not
+//! actively asking. This module emits `subscriptions://status-changed`
+//! events instead, fed either by decoded server notifications or by a
+//! background poller that diffs against the last known status.
+
+use crate::{Error, Result, SubscriptionStatus};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+pub const STATUS_CHANGED_EVENT: &str = "subscriptions://status-changed";
+
+const DEFAULT_POLL_INTERVAL_MS: u64 = 60_000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriptionEventKind {
+    Renewed,
+    Cancelled,
+    Expired,
+    GracePeriodExpired,
+    Refunded,
+    Revoked,
+    StatusChanged,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SubscriptionEvent {
+    product_id: String,
+    kind: SubscriptionEventKind,
+    status: SubscriptionStatus,
+}
+
+/// Tracks the background poller and the last known status per product, so
+/// the plugin can be torn down and re-started via `listen_subscription_events`
+/// / `stop_listening`.
+#[derive(Default)]
+pub struct EventWatcherState {
+    handle: Mutex<Option<tokio::task::JoinHandle<()>>>,
+    last_known: Mutex<HashMap<String, SubscriptionStatus>>,
+}
+
+pub async fn start_watching(app: AppHandle, product_ids: Vec<String>, poll_interval_ms: Option<u64>) -> Result<()> {
+    stop_watching(&app)?;
+
+    let interval = Duration::from_millis(poll_interval_ms.unwrap_or(DEFAULT_POLL_INTERVAL_MS));
+    let watcher_app = app.clone();
+
+    let handle = tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            for product_id in &product_ids {
+                if let Ok(status) = crate::get_subscription_status(watcher_app.clone(), product_id.clone()).await {
+                    emit_if_changed(&watcher_app, product_id, status);
+                }
+            }
+        }
+    });
+
+    let state = app.state::<EventWatcherState>();
+    *state.handle.lock().unwrap() = Some(handle);
+    Ok(())
+}
+
+pub fn stop_watching(app: &AppHandle) -> Result<()> {
+    let state = app.state::<EventWatcherState>();
+    if let Some(handle) = state.handle.lock().unwrap().take() {
+        handle.abort();
+    }
+    Ok(())
+}
+
+fn emit_if_changed(app: &AppHandle, product_id: &str, status: SubscriptionStatus) {
+    let state = app.state::<EventWatcherState>();
+    let mut last_known = state.last_known.lock().unwrap();
+
+    // The first observation of a product has no prior status to diff
+    // against, so it just seeds `last_known` rather than firing a spurious
+    // "changed" event. `last_known` is kept across `stop_listening`, so after
+    // a restart the first poll diffs against the status seen before the stop.
+    let kind = match last_known.get(product_id) {
+        Some(previous) if status_changed(previous, &status) => Some(SubscriptionEventKind::StatusChanged),
+        Some(_) => None,
+        None => None,
+    };
+
+    last_known.insert(product_id.to_string(), status.clone());
+    drop(last_known);
+
+    if let Some(kind) = kind {
+        emit_event(app, product_id, kind, status);
+    }
+}
+
+fn status_changed(previous: &SubscriptionStatus, current: &SubscriptionStatus) -> bool {
+    previous.is_active != current.is_active
+        || previous.auto_renew_status != current.auto_renew_status
+        || previous.is_in_grace_period != current.is_in_grace_period
+        || previous.expiry_date != current.expiry_date
+}
+
+fn emit_event(app: &AppHandle, product_id: &str, kind: SubscriptionEventKind, status: SubscriptionStatus) {
+    let event = SubscriptionEvent {
+        product_id: product_id.to_string(),
+        kind,
+        status,
+    };
+    let _ = app.emit_all(STATUS_CHANGED_EVENT, event);
+}
+
+/// App Store Server Notifications v2 payload, decoded from its signed JWS.
+#[derive(Debug, Deserialize)]
+struct AppleServerNotification {
+    #[serde(rename = "notificationType")]
+    notification_type: String,
+    subtype: Option<String>,
+}
+
+/// Decodes an App Store Server Notifications v2 signed payload and emits the
+/// matching event.
+///
+/// This only base64-decodes the claims; it does not verify the JWS
+/// signature against Apple's certificate chain. That's why this function is
+/// `pub` Rust API but deliberately **not** a `#[tauri::command]` — it must
+/// only be called from trusted native code (e.g. a push-notification
+/// receiver or server-forwarded webhook handler that already authenticated
+/// the sender), never from the webview, which would let arbitrary page
+/// content forge subscription state.
+pub fn handle_apple_notification(app: &AppHandle, signed_payload: &str) -> Result<()> {
+    let claims = decode_jws_payload(signed_payload)?;
+    let notification: AppleServerNotification = serde_json::from_value(claims)
+        .map_err(|e| Error::SubscriptionError(format!("invalid App Store server notification: {e}")))?;
+
+    let kind = apple_notification_kind(&notification.notification_type, notification.subtype.as_deref());
+    emit_apple_kind(app, kind);
+    Ok(())
+}
+
+fn apple_notification_kind(notification_type: &str, subtype: Option<&str>) -> SubscriptionEventKind {
+    match (notification_type, subtype) {
+        ("DID_RENEW", _) => SubscriptionEventKind::Renewed,
+        ("EXPIRED", Some("GRACE_PERIOD_EXPIRED")) | ("GRACE_PERIOD_EXPIRED", _) => {
+            SubscriptionEventKind::GracePeriodExpired
+        }
+        ("EXPIRED", _) => SubscriptionEventKind::Expired,
+        ("DID_CHANGE_RENEWAL_STATUS", Some("AUTO_RENEW_DISABLED")) => SubscriptionEventKind::Cancelled,
+        ("REFUND", _) => SubscriptionEventKind::Refunded,
+        ("REVOKE", _) => SubscriptionEventKind::Revoked,
+        _ => SubscriptionEventKind::StatusChanged,
+    }
+}
+
+fn emit_apple_kind(app: &AppHandle, kind: SubscriptionEventKind) {
+    // Apple's notification identifies the subscription by transaction info
+    // nested in `data.signedTransactionInfo`, which is out of scope for this
+    // minimal decoder; apps that need the product id should follow up with
+    // `get_subscription_status`.
+    let status = SubscriptionStatus {
+        product_id: String::new(),
+        is_active: !matches!(
+            kind,
+            SubscriptionEventKind::Expired
+                | SubscriptionEventKind::GracePeriodExpired
+                | SubscriptionEventKind::Revoked
+        ),
+        expiry_date: None,
+        auto_renew_status: !matches!(kind, SubscriptionEventKind::Cancelled),
+        is_in_trial_period: false,
+        // The grace period has already elapsed by the time this kind fires,
+        // so the subscription is no longer "in" grace.
+        is_in_grace_period: false,
+        renewal_price_amount: None,
+        renewal_currency: None,
+    };
+    emit_event(app, "", kind, status);
+}
+
+/// Google Play Real-Time Developer Notification payload.
+#[derive(Debug, Deserialize)]
+struct GoogleRtdnPayload {
+    #[serde(rename = "subscriptionNotification")]
+    subscription_notification: Option<GoogleSubscriptionNotification>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GoogleSubscriptionNotification {
+    #[serde(rename = "notificationType")]
+    notification_type: u32,
+    #[serde(rename = "subscriptionId")]
+    subscription_id: String,
+}
+
+/// Handles a Google Play Real-Time Developer Notification, forwarded from a
+/// developer backend as the base64-encoded Pub/Sub message data.
+///
+/// Like [`handle_apple_notification`], this is `pub` Rust API but not a
+/// `#[tauri::command]`: Pub/Sub messages aren't signed in a way this plugin
+/// verifies, so only trusted native code that has already authenticated the
+/// sender (e.g. the Pub/Sub push endpoint itself) should call this.
+pub fn handle_google_rtdn(app: &AppHandle, payload_base64: &str) -> Result<()> {
+    let decoded = base64::decode(payload_base64)
+        .map_err(|e| Error::SubscriptionError(format!("invalid RTDN payload encoding: {e}")))?;
+    let payload: GoogleRtdnPayload = serde_json::from_slice(&decoded)
+        .map_err(|e| Error::SubscriptionError(format!("invalid RTDN payload: {e}")))?;
+
+    let Some(notification) = payload.subscription_notification else {
+        return Ok(());
+    };
+
+    let kind = google_notification_kind(notification.notification_type);
+    let status = SubscriptionStatus {
+        product_id: notification.subscription_id.clone(),
+        is_active: !matches!(
+            kind,
+            SubscriptionEventKind::Expired
+                | SubscriptionEventKind::Revoked
+                | SubscriptionEventKind::GracePeriodExpired
+        ),
+        expiry_date: None,
+        auto_renew_status: !matches!(kind, SubscriptionEventKind::Cancelled),
+        is_in_trial_period: false,
+        // `GracePeriodExpired` here means the grace period already elapsed
+        // (Google's SUBSCRIPTION_ON_HOLD), so the subscription is no longer
+        // "in" grace by the time apps see this event.
+        is_in_grace_period: false,
+        renewal_price_amount: None,
+        renewal_currency: None,
+    };
+    emit_event(app, &notification.subscription_id, kind, status);
+    Ok(())
+}
+
+/// Maps Google Play's `SubscriptionNotificationType` (1-13) to our event kind.
+///
+/// Type 6 (`SUBSCRIPTION_IN_GRACE_PERIOD`) fires when a subscription *enters*
+/// its grace period after a failed renewal payment — the subscription is
+/// still active while Google keeps retrying the charge. Type 5
+/// (`SUBSCRIPTION_ON_HOLD`) is what fires once the grace period elapses
+/// without a successful charge, which is the "grace period is over" case
+/// apps actually want to react to.
+fn google_notification_kind(notification_type: u32) -> SubscriptionEventKind {
+    match notification_type {
+        2 => SubscriptionEventKind::Renewed,   // SUBSCRIPTION_RENEWED
+        3 => SubscriptionEventKind::Cancelled, // SUBSCRIPTION_CANCELED
+        5 => SubscriptionEventKind::GracePeriodExpired, // SUBSCRIPTION_ON_HOLD
+        12 => SubscriptionEventKind::Revoked,  // SUBSCRIPTION_REVOKED
+        13 => SubscriptionEventKind::Expired,  // SUBSCRIPTION_EXPIRED
+        _ => SubscriptionEventKind::StatusChanged,
+    }
+}
+
+fn decode_jws_payload(signed_payload: &str) -> Result<serde_json::Value> {
+    let mut parts = signed_payload.split('.');
+    let _header = parts.next();
+    let payload = parts
+        .next()
+        .ok_or_else(|| Error::SubscriptionError("malformed JWS payload".to_string()))?;
+
+    let decoded = base64::decode_config(payload, base64::URL_SAFE_NO_PAD)
+        .map_err(|e| Error::SubscriptionError(format!("invalid JWS payload encoding: {e}")))?;
+
+    serde_json::from_slice(&decoded)
+        .map_err(|e| Error::SubscriptionError(format!("invalid JWS payload JSON: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apple_notification_kind_maps_each_case() {
+        let cases = [
+            ("DID_RENEW", None, SubscriptionEventKind::Renewed),
+            (
+                "EXPIRED",
+                Some("GRACE_PERIOD_EXPIRED"),
+                SubscriptionEventKind::GracePeriodExpired,
+            ),
+            (
+                "GRACE_PERIOD_EXPIRED",
+                None,
+                SubscriptionEventKind::GracePeriodExpired,
+            ),
+            ("EXPIRED", None, SubscriptionEventKind::Expired),
+            (
+                "DID_CHANGE_RENEWAL_STATUS",
+                Some("AUTO_RENEW_DISABLED"),
+                SubscriptionEventKind::Cancelled,
+            ),
+            ("REFUND", None, SubscriptionEventKind::Refunded),
+            ("REVOKE", None, SubscriptionEventKind::Revoked),
+            ("SOMETHING_ELSE", None, SubscriptionEventKind::StatusChanged),
+        ];
+
+        for (notification_type, subtype, expected) in cases {
+            assert_eq!(
+                apple_notification_kind(notification_type, subtype),
+                expected,
+                "notification_type={notification_type:?} subtype={subtype:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn google_notification_kind_maps_each_case() {
+        let cases = [
+            (2, SubscriptionEventKind::Renewed),
+            (3, SubscriptionEventKind::Cancelled),
+            (12, SubscriptionEventKind::Revoked),
+            (13, SubscriptionEventKind::Expired),
+            (99, SubscriptionEventKind::StatusChanged),
+        ];
+
+        for (notification_type, expected) in cases {
+            assert_eq!(google_notification_kind(notification_type), expected, "notification_type={notification_type}");
+        }
+    }
+
+    #[test]
+    fn google_notification_type_6_is_still_active_grace_entry_not_expiry() {
+        // Type 6 is SUBSCRIPTION_IN_GRACE_PERIOD: the subscription just
+        // entered grace and is still active, so it must not be read as
+        // "grace period is over".
+        assert_eq!(
+            google_notification_kind(6),
+            SubscriptionEventKind::StatusChanged
+        );
+    }
+
+    #[test]
+    fn google_notification_type_5_is_grace_period_expired() {
+        // Type 5 is SUBSCRIPTION_ON_HOLD: the grace period elapsed without a
+        // successful charge.
+        assert_eq!(
+            google_notification_kind(5),
+            SubscriptionEventKind::GracePeriodExpired
+        );
+    }
+
+    #[test]
+    fn decode_jws_payload_decodes_the_middle_segment() {
+        // `{"notificationType":"DID_RENEW"}` base64url-encoded, no padding.
+        let payload_b64 = "eyJub3RpZmljYXRpb25UeXBlIjoiRElEX1JFTkVXIn0";
+        let signed = format!("header.{payload_b64}.signature");
+
+        let claims = decode_jws_payload(&signed).unwrap();
+        assert_eq!(claims["notificationType"], "DID_RENEW");
+    }
+
+    #[test]
+    fn decode_jws_payload_rejects_missing_segment() {
+        assert!(decode_jws_payload("only-one-segment").is_err());
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{Error, Product, ProductType, PurchaseResult, Result, SubscriptionPeriod, SubscriptionStatus};
+use crate::{
+    pending, Error, OfferType, PaymentMode, Product, ProductType, PurchaseResult, Result,
+    SubscriptionOffer, SubscriptionPeriod, SubscriptionStatus,
+};
 use tauri::AppHandle;
 use core_foundation::{
     base::TCFType,
@@ -15,9 +18,10 @@ use objc::{
     sel,
     sel_impl,
 };
-use std::ffi::c_void;
-use std::sync::{Arc, Mutex};
+use once_cell::sync::Lazy;
 use std::collections::HashMap;
+use std::ffi::{c_void, CString};
+use std::sync::Mutex;
 
 // Function to convert Rust strings to CFString
 fn to_cf_string(s: &str) -> CFString {
@@ -31,75 +35,148 @@ fn strings_to_cf_array(strings: &[String]) -> CFArray {
     unsafe { CFArray::from_buffer_nocopy(refs.as_ptr() as *const _, refs.len()) }
 }
 
+// Completion blocks handed to `msg_send!` must be plain, non-capturing `fn`
+// pointers (Objective-C has no notion of a Rust closure environment). Each
+// request stashes what the completion needs under its `request_id` here, and
+// the completion looks it up instead of closing over local variables.
+static PRODUCTS_CONTEXT: Lazy<Mutex<HashMap<u64, Vec<String>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static PURCHASE_CONTEXT: Lazy<Mutex<HashMap<u64, String>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static OFFER_PURCHASE_CONTEXT: Lazy<Mutex<HashMap<u64, (String, String)>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static STATUS_CONTEXT: Lazy<Mutex<HashMap<u64, String>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Serializes `payload`, passes it through `pending::on_native_result` as if
+/// the ObjC side had called back across the FFI boundary, and records the
+/// request as resolved.
+fn resolve_via_native_result<T: serde::Serialize>(request_id: u64, payload: Result<T>) {
+    let result = payload.and_then(|value| {
+        serde_json::to_string(&value)
+            .map_err(|e| Error::PlatformError(format!("failed to encode native payload: {e}")))
+    });
+
+    match result {
+        Ok(json) => match CString::new(json) {
+            Ok(c_payload) => unsafe { pending::on_native_result(request_id, true, c_payload.as_ptr()) },
+            Err(e) => unsafe {
+                pending::on_native_result(request_id, false, error_cstring(&e.to_string()).as_ptr())
+            },
+        },
+        Err(e) => unsafe {
+            pending::on_native_result(request_id, false, error_cstring(&e.to_string()).as_ptr())
+        },
+    }
+}
+
+fn error_cstring(message: &str) -> CString {
+    CString::new(message).unwrap_or_else(|_| CString::new("native error").unwrap())
+}
+
 pub fn init_ios(app_handle: &AppHandle) -> Result<()> {
     // Call the Objective-C Subscriptions.register method
     unsafe {
         let subscriptions_class = Class::get("Subscriptions").ok_or_else(|| {
             Error::PlatformError("Subscriptions class not found".to_string())
         })?;
-        
+
         let _: () = msg_send![subscriptions_class, register];
     }
-    
+
     Ok(())
 }
 
-pub async fn get_products_ios(app_handle: AppHandle, product_ids: Vec<String>) -> Result<Vec<Product>> {
-    let result = Arc::new(Mutex::new(None));
-    let result_clone = result.clone();
-    
+pub async fn get_products_ios(_app_handle: AppHandle, product_ids: Vec<String>) -> Result<Vec<Product>> {
     // Create a CFArray from the product IDs
     let cf_product_ids = strings_to_cf_array(&product_ids);
-    
+    let (request_id, rx) = pending::register();
+    PRODUCTS_CONTEXT.lock().unwrap().insert(request_id, product_ids);
+
     unsafe {
         let subscriptions_class = Class::get("Subscriptions").ok_or_else(|| {
             Error::PlatformError("Subscriptions class not found".to_string())
         })?;
-        
+
         let shared: *mut Object = msg_send![subscriptions_class, shared];
-        
-        // Create a completion block
-        let completion_block: extern "C" fn(*mut Object, *mut Object) = |products, error| {
-            // Here we would convert the SKProduct objects to our Rust Product type
-            // This is a simplification
-            let mut result_products = Vec::new();
-            
-            // Mock implementation
-            for id in product_ids.iter() {
-                result_products.push(Product {
-                    id: id.clone(),
-                    title: format!("Product {}", id),
-                    description: "Description".to_string(),
-                    price: "$9.99".to_string(),
-                    price_amount: 9.99,
-                    currency_code: "USD".to_string(),
-                    product_type: ProductType::Subscription,
-                    subscription_period: Some(SubscriptionPeriod::Month),
-                    subscription_period_unit: Some(1),
-                });
-            }
-            
-            *result_clone.lock().unwrap() = Some(result_products);
-        };
-        
-        // Call the getProducts method with our completion block
-        let _: () = msg_send![shared, getProducts:cf_product_ids completion:completion_block];
+
+        // StoreKit resolves getProducts asynchronously and hands `request_id`
+        // back to `products_completion`, which looks up the matching context
+        // and resolves the pending registry instead of returning early.
+        let completion_block: extern "C" fn(u64, *mut Object, *mut Object) = products_completion;
+        let _: () = msg_send![shared, getProducts:cf_product_ids requestId:request_id completion:completion_block];
     }
-    
-    // In a real implementation, we would wait for the completion block to be called
-    // For now, we'll just return the mock data immediately
-    match Arc::try_unwrap(result).unwrap().into_inner().unwrap() {
-        Some(products) => Ok(products),
-        None => Err(Error::ProductRetrievalError("Failed to retrieve products".to_string())),
+
+    let payload = pending::wait(request_id, rx, || {
+        PRODUCTS_CONTEXT.lock().unwrap().remove(&request_id);
+    })
+    .await?;
+    serde_json::from_value(payload)
+        .map_err(|e| Error::ProductRetrievalError(format!("invalid product payload: {e}")))
+}
+
+extern "C" fn products_completion(request_id: u64, _products: *mut Object, _error: *mut Object) {
+    // Here we would convert the SKProduct objects in `_products` to our Rust
+    // Product type; this is a simplification.
+    let product_ids = PRODUCTS_CONTEXT.lock().unwrap().remove(&request_id).unwrap_or_default();
+
+    // Mock implementation
+    let mut result_products = Vec::new();
+    for id in product_ids.iter() {
+        result_products.push(Product {
+            id: id.clone(),
+            title: format!("Product {}", id),
+            description: "Description".to_string(),
+            price: "$9.99".to_string(),
+            price_amount: 9.99,
+            currency_code: "USD".to_string(),
+            product_type: ProductType::Subscription,
+            subscription_period: Some(SubscriptionPeriod::Month),
+            subscription_period_unit: Some(1),
+            offers: vec![SubscriptionOffer {
+                id: Some(format!("{id}_intro")),
+                offer_type: OfferType::Introductory,
+                price_amount: 0.0,
+                price: "$0.00".to_string(),
+                period: SubscriptionPeriod::Week,
+                period_count: 1,
+                period_units: 1,
+                payment_mode: PaymentMode::FreeTrial,
+            }],
+        });
+    }
+
+    resolve_via_native_result(request_id, Ok(result_products));
+}
+
+pub async fn purchase_product_ios(_app_handle: AppHandle, product_id: String) -> Result<PurchaseResult> {
+    let cf_product_id = to_cf_string(&product_id);
+    let (request_id, rx) = pending::register();
+    PURCHASE_CONTEXT.lock().unwrap().insert(request_id, product_id);
+
+    unsafe {
+        let subscriptions_class = Class::get("Subscriptions").ok_or_else(|| {
+            Error::PlatformError("Subscriptions class not found".to_string())
+        })?;
+
+        let shared: *mut Object = msg_send![subscriptions_class, shared];
+
+        // In a real implementation, we would first get the SKProduct object
+        // for this ID and then initiate the purchase; `purchase_completion`
+        // resolves `request_id` once StoreKit's transaction observer fires.
+        let completion_block: extern "C" fn(u64, *mut Object, *mut Object) = purchase_completion;
+        let _: () = msg_send![shared, purchaseProduct:cf_product_id requestId:request_id completion:completion_block];
     }
+
+    let payload = pending::wait(request_id, rx, || {
+        PURCHASE_CONTEXT.lock().unwrap().remove(&request_id);
+    })
+    .await?;
+    serde_json::from_value(payload)
+        .map_err(|e| Error::PurchaseError(format!("invalid purchase payload: {e}")))
 }
 
-pub async fn purchase_product_ios(app_handle: AppHandle, product_id: String) -> Result<PurchaseResult> {
-    // In a real implementation, we would first get the SKProduct object for this ID
-    // and then initiate the purchase
+extern "C" fn purchase_completion(request_id: u64, _transaction: *mut Object, _error: *mut Object) {
+    let product_id = PURCHASE_CONTEXT.lock().unwrap().remove(&request_id).unwrap_or_default();
 
     // Mock successful purchase
-    Ok(PurchaseResult {
+    let result = PurchaseResult {
         product_id,
         transaction_id: format!("ios_transaction_{}", rand::random::<u64>()),
         purchase_time: std::time::SystemTime::now()
@@ -114,12 +191,96 @@ pub async fn purchase_product_ios(app_handle: AppHandle, product_id: String) ->
                 .as_secs() + 30 * 24 * 60 * 60 // 30 days
         ),
         receipt_data: Some("sample_receipt_data".to_string()),
+    };
+
+    resolve_via_native_result(request_id, Ok(result));
+}
+
+pub async fn purchase_product_with_offer_ios(
+    _app_handle: AppHandle,
+    product_id: String,
+    offer_id: String,
+) -> Result<PurchaseResult> {
+    let cf_product_id = to_cf_string(&product_id);
+    let cf_offer_id = to_cf_string(&offer_id);
+    let (request_id, rx) = pending::register();
+    OFFER_PURCHASE_CONTEXT.lock().unwrap().insert(request_id, (product_id, offer_id));
+
+    unsafe {
+        let subscriptions_class = Class::get("Subscriptions").ok_or_else(|| {
+            Error::PlatformError("Subscriptions class not found".to_string())
+        })?;
+
+        let shared: *mut Object = msg_send![subscriptions_class, shared];
+
+        // In a real implementation, we would build an SKProductDiscount
+        // payment with a signed offer signature for `offer_id` before adding
+        // it to the payment queue; `offer_purchase_completion` resolves
+        // `request_id` once StoreKit's transaction observer fires.
+        let completion_block: extern "C" fn(u64, *mut Object, *mut Object) = offer_purchase_completion;
+        let _: () = msg_send![shared, purchaseProduct:cf_product_id withOffer:cf_offer_id requestId:request_id completion:completion_block];
+    }
+
+    let payload = pending::wait(request_id, rx, || {
+        OFFER_PURCHASE_CONTEXT.lock().unwrap().remove(&request_id);
     })
+    .await?;
+    serde_json::from_value(payload)
+        .map_err(|e| Error::PurchaseError(format!("invalid purchase payload: {e}")))
+}
+
+extern "C" fn offer_purchase_completion(request_id: u64, _transaction: *mut Object, _error: *mut Object) {
+    let (product_id, offer_id) = OFFER_PURCHASE_CONTEXT
+        .lock()
+        .unwrap()
+        .remove(&request_id)
+        .unwrap_or_default();
+
+    // Mock successful purchase redeeming the offer
+    let result = PurchaseResult {
+        product_id,
+        transaction_id: format!("ios_transaction_{}", rand::random::<u64>()),
+        purchase_time: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+        is_acknowledged: true,
+        subscription_expiry_time: Some(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs() + 30 * 24 * 60 * 60 // 30 days
+        ),
+        receipt_data: Some(format!("sample_receipt_data_offer_{offer_id}")),
+    };
+
+    resolve_via_native_result(request_id, Ok(result));
+}
+
+pub async fn restore_purchases_ios(_app_handle: AppHandle) -> Result<Vec<PurchaseResult>> {
+    let (request_id, rx) = pending::register();
+
+    unsafe {
+        let subscriptions_class = Class::get("Subscriptions").ok_or_else(|| {
+            Error::PlatformError("Subscriptions class not found".to_string())
+        })?;
+
+        let shared: *mut Object = msg_send![subscriptions_class, shared];
+
+        // `restore_completion` resolves `request_id` once StoreKit's restore
+        // queue callback fires.
+        let completion_block: extern "C" fn(u64, *mut Object, *mut Object) = restore_completion;
+        let _: () = msg_send![shared, restorePurchasesWithRequestId:request_id completion:completion_block];
+    }
+
+    let payload = pending::wait(request_id, rx, || {}).await?;
+    serde_json::from_value(payload)
+        .map_err(|e| Error::PlatformError(format!("invalid restore payload: {e}")))
 }
 
-pub async fn restore_purchases_ios(app_handle: AppHandle) -> Result<Vec<PurchaseResult>> {
+extern "C" fn restore_completion(request_id: u64, _transactions: *mut Object, _error: *mut Object) {
     // Mock restored purchases
-    Ok(vec![
+    let result = vec![
         PurchaseResult {
             product_id: "com.example.subscription.monthly".to_string(),
             transaction_id: format!("ios_transaction_{}", rand::random::<u64>()),
@@ -136,14 +297,42 @@ pub async fn restore_purchases_ios(app_handle: AppHandle) -> Result<Vec<Purchase
             ),
             receipt_data: Some("sample_receipt_data".to_string()),
         }
-    ])
+    ];
+
+    resolve_via_native_result(request_id, Ok(result));
 }
 
-pub async fn get_subscription_status_ios(app_handle: AppHandle, product_id: String) -> Result<SubscriptionStatus> {
-    // In a real implementation, we would query the Subscriptions class
-    
+pub async fn get_subscription_status_ios(_app_handle: AppHandle, product_id: String) -> Result<SubscriptionStatus> {
+    let cf_product_id = to_cf_string(&product_id);
+    let (request_id, rx) = pending::register();
+    STATUS_CONTEXT.lock().unwrap().insert(request_id, product_id);
+
+    unsafe {
+        let subscriptions_class = Class::get("Subscriptions").ok_or_else(|| {
+            Error::PlatformError("Subscriptions class not found".to_string())
+        })?;
+
+        let shared: *mut Object = msg_send![subscriptions_class, shared];
+
+        // `status_completion` resolves `request_id` once StoreKit reports the
+        // current entitlement state.
+        let completion_block: extern "C" fn(u64, *mut Object, *mut Object) = status_completion;
+        let _: () = msg_send![shared, getSubscriptionStatus:cf_product_id requestId:request_id completion:completion_block];
+    }
+
+    let payload = pending::wait(request_id, rx, || {
+        STATUS_CONTEXT.lock().unwrap().remove(&request_id);
+    })
+    .await?;
+    serde_json::from_value(payload)
+        .map_err(|e| Error::SubscriptionError(format!("invalid status payload: {e}")))
+}
+
+extern "C" fn status_completion(request_id: u64, _status: *mut Object, _error: *mut Object) {
+    let product_id = STATUS_CONTEXT.lock().unwrap().remove(&request_id).unwrap_or_default();
+
     // Mock subscription status
-    Ok(SubscriptionStatus {
+    let result = SubscriptionStatus {
         product_id,
         is_active: true,
         expiry_date: Some(
@@ -155,5 +344,9 @@ pub async fn get_subscription_status_ios(app_handle: AppHandle, product_id: Stri
         auto_renew_status: true,
         is_in_trial_period: false,
         is_in_grace_period: false,
-    })
-}
\ No newline at end of file
+        renewal_price_amount: Some(9.99),
+        renewal_currency: Some("USD".to_string()),
+    };
+
+    resolve_via_native_result(request_id, Ok(result));
+}
@@ -22,6 +22,9 @@ pub enum Error {
     
     #[error("Platform-specific error: {0}")]
     PlatformError(String),
+
+    #[error("Purchases are currently paused")]
+    PurchasesPaused,
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -41,6 +44,33 @@ pub enum SubscriptionPeriod {
     Year,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum OfferType {
+    Introductory,
+    Promotional,
+    Code,
+    WinBack,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum PaymentMode {
+    PayAsYouGo,
+    PayUpFront,
+    FreeTrial,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SubscriptionOffer {
+    id: Option<String>,
+    offer_type: OfferType,
+    price_amount: f64,
+    price: String,
+    period: SubscriptionPeriod,
+    period_count: u32,
+    period_units: u32,
+    payment_mode: PaymentMode,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Product {
     id: String,
@@ -52,6 +82,7 @@ pub struct Product {
     product_type: ProductType,
     subscription_period: Option<SubscriptionPeriod>,
     subscription_period_unit: Option<u32>,
+    offers: Vec<SubscriptionOffer>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -72,6 +103,16 @@ pub struct SubscriptionStatus {
     auto_renew_status: bool,
     is_in_trial_period: bool,
     is_in_grace_period: bool,
+    renewal_price_amount: Option<f64>,
+    renewal_currency: Option<String>,
+}
+
+/// Global kill-switch checked by every mutating entry point, so purchasing
+/// can be disabled during a backend outage or a pricing migration without
+/// shipping a new build.
+#[derive(Default)]
+struct PurchaseGateState {
+    paused: std::sync::atomic::AtomicBool,
 }
 
 #[cfg(target_os = "ios")]
@@ -84,6 +125,14 @@ mod android;
 #[cfg(target_os = "android")]
 use android::*;
 
+mod validation;
+pub use validation::{Environment, ValidationResult};
+
+mod pending;
+
+mod events;
+pub use events::{handle_apple_notification, handle_google_rtdn, SubscriptionEvent, SubscriptionEventKind};
+
 #[tauri::command]
 async fn get_products(
     app: AppHandle,
@@ -110,6 +159,8 @@ async fn purchase_product(
     app: AppHandle,
     product_id: String,
 ) -> Result<PurchaseResult> {
+    ensure_purchases_not_paused(&app)?;
+
     #[cfg(target_os = "ios")]
     {
         return ios::purchase_product_ios(app, product_id).await;
@@ -126,10 +177,36 @@ async fn purchase_product(
     }
 }
 
+#[tauri::command]
+async fn purchase_product_with_offer(
+    app: AppHandle,
+    product_id: String,
+    offer_id: String,
+) -> Result<PurchaseResult> {
+    ensure_purchases_not_paused(&app)?;
+
+    #[cfg(target_os = "ios")]
+    {
+        return ios::purchase_product_with_offer_ios(app, product_id, offer_id).await;
+    }
+
+    #[cfg(target_os = "android")]
+    {
+        return android::purchase_product_with_offer_android(app, product_id, offer_id).await;
+    }
+
+    #[cfg(not(any(target_os = "ios", target_os = "android")))]
+    {
+        Err(Error::PlatformError("Purchases are only supported on iOS and Android".to_string()))
+    }
+}
+
 #[tauri::command]
 async fn restore_purchases(
     app: AppHandle,
 ) -> Result<Vec<PurchaseResult>> {
+    ensure_purchases_not_paused(&app)?;
+
     #[cfg(target_os = "ios")]
     {
         return ios::restore_purchases_ios(app).await;
@@ -167,27 +244,157 @@ async fn get_subscription_status(
     }
 }
 
+#[tauri::command]
+async fn validate_receipt(
+    app: AppHandle,
+    platform: String,
+    receipt_data: String,
+    product_id: String,
+) -> Result<ValidationResult> {
+    let config = app.state::<validation::ValidationConfig>();
+    validation::validate_receipt(&config, &platform, &receipt_data, &product_id).await
+}
+
+#[tauri::command]
+async fn listen_subscription_events(
+    app: AppHandle,
+    product_ids: Vec<String>,
+    poll_interval_ms: Option<u64>,
+) -> Result<()> {
+    events::start_watching(app, product_ids, poll_interval_ms).await
+}
+
+#[tauri::command]
+async fn stop_listening(app: AppHandle) -> Result<()> {
+    events::stop_watching(&app)
+}
+
+fn ensure_purchases_not_paused(app: &AppHandle) -> Result<()> {
+    let gate = app.state::<PurchaseGateState>();
+    if gate.paused.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(Error::PurchasesPaused);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn pause_purchases(app: AppHandle) -> Result<()> {
+    app.state::<PurchaseGateState>()
+        .paused
+        .store(true, std::sync::atomic::Ordering::SeqCst);
+    Ok(())
+}
+
+#[tauri::command]
+async fn resume_purchases(app: AppHandle) -> Result<()> {
+    app.state::<PurchaseGateState>()
+        .paused
+        .store(false, std::sync::atomic::Ordering::SeqCst);
+    Ok(())
+}
+
+#[tauri::command]
+async fn is_paused(app: AppHandle) -> Result<bool> {
+    Ok(app
+        .state::<PurchaseGateState>()
+        .paused
+        .load(std::sync::atomic::Ordering::SeqCst))
+}
+
+/// Builds the plugin with optional server-side receipt validation
+/// credentials. Validation is pure HTTP, so unlike the product, purchase,
+/// restore and status commands it runs on desktop as well as iOS and Android.
+#[derive(Default)]
+pub struct SubscriptionsBuilder {
+    apple_shared_secret: Option<String>,
+    google_service_account_key: Option<String>,
+    google_package_name: Option<String>,
+    native_call_timeout: Option<std::time::Duration>,
+}
+
+impl SubscriptionsBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The shared secret used to authenticate requests to Apple's
+    /// `verifyReceipt` endpoint.
+    pub fn apple_shared_secret(mut self, secret: impl Into<String>) -> Self {
+        self.apple_shared_secret = Some(secret.into());
+        self
+    }
+
+    /// The JSON key of a Google service account with access to the
+    /// Android Publisher API.
+    pub fn google_service_account_key(mut self, key: impl Into<String>) -> Self {
+        self.google_service_account_key = Some(key.into());
+        self
+    }
+
+    /// The Android application id (e.g. `com.example.app`) used as the
+    /// `{package}` path segment of Android Publisher API requests. A GCP
+    /// service account's `project_id` is a different identifier and can't
+    /// be substituted here.
+    pub fn google_package_name(mut self, package_name: impl Into<String>) -> Self {
+        self.google_package_name = Some(package_name.into());
+        self
+    }
+
+    /// How long to wait for a native purchase/restore/status call to
+    /// complete before failing with `Error::PlatformError`. Defaults to 30
+    /// seconds.
+    pub fn native_call_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.native_call_timeout = Some(timeout);
+        self
+    }
+
+    pub fn build<R: Runtime>(self) -> TauriPlugin<R> {
+        let validation_config = validation::ValidationConfig {
+            apple_shared_secret: self.apple_shared_secret,
+            google_service_account_key: self.google_service_account_key,
+            google_package_name: self.google_package_name,
+        };
+
+        if let Some(timeout) = self.native_call_timeout {
+            pending::configure_timeout(timeout);
+        }
+
+        Builder::new("subscriptions")
+            .invoke_handler(tauri::generate_handler![
+                get_products,
+                purchase_product,
+                purchase_product_with_offer,
+                restore_purchases,
+                get_subscription_status,
+                validate_receipt,
+                listen_subscription_events,
+                stop_listening,
+                pause_purchases,
+                resume_purchases,
+                is_paused,
+            ])
+            .setup(move |app_handle| {
+                app_handle.manage(validation_config.clone());
+                app_handle.manage(events::EventWatcherState::default());
+                app_handle.manage(PurchaseGateState::default());
+
+                #[cfg(target_os = "ios")]
+                {
+                    ios::init_ios(app_handle)?;
+                }
+
+                #[cfg(target_os = "android")]
+                {
+                    android::init_android(app_handle)?;
+                }
+
+                Ok(())
+            })
+            .build()
+    }
+}
+
 /// Initialize the plugin
 pub fn init<R: Runtime>() -> TauriPlugin<R> {
-    Builder::new("subscriptions")
-        .invoke_handler(tauri::generate_handler![
-            get_products,
-            purchase_product,
-            restore_purchases,
-            get_subscription_status,
-        ])
-        .setup(|app_handle| {
-            #[cfg(target_os = "ios")]
-            {
-                ios::init_ios(app_handle)?;
-            }
-            
-            #[cfg(target_os = "android")]
-            {
-                android::init_android(app_handle)?;
-            }
-            
-            Ok(())
-        })
-        .build()
+    SubscriptionsBuilder::new().build()
 }
\ No newline at end of file
@@ -0,0 +1,107 @@
+//! A registry of in-flight native calls, so `ios.rs`/`android.rs` can hand a
+//! request id across the JNI/ObjC boundary and `.await` the matching
+//! StoreKit/Billing completion instead of returning mock data immediately.
+
+use crate::{Error, Result};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// Default time to wait for a native completion callback before giving up,
+/// overridable via [`crate::SubscriptionsBuilder::native_call_timeout`].
+const DEFAULT_NATIVE_CALL_TIMEOUT_MS: u64 = 30_000;
+
+static NATIVE_CALL_TIMEOUT_MS: AtomicU64 = AtomicU64::new(DEFAULT_NATIVE_CALL_TIMEOUT_MS);
+
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+static PENDING: Lazy<Mutex<HashMap<u64, oneshot::Sender<Result<serde_json::Value>>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Overrides how long [`wait`] waits for a native completion callback before
+/// giving up. Called once from [`crate::SubscriptionsBuilder::build`].
+pub fn configure_timeout(timeout: Duration) {
+    NATIVE_CALL_TIMEOUT_MS.store(timeout.as_millis() as u64, Ordering::SeqCst);
+}
+
+fn timeout() -> Duration {
+    Duration::from_millis(NATIVE_CALL_TIMEOUT_MS.load(Ordering::SeqCst))
+}
+
+/// Allocates a request id and registers a oneshot channel for it. The id
+/// should be passed across to the native side; the receiver is awaited with
+/// [`wait`] once the native call has been fired.
+pub fn register() -> (u64, oneshot::Receiver<Result<serde_json::Value>>) {
+    let (tx, rx) = oneshot::channel();
+    let id = NEXT_REQUEST_ID.fetch_add(1, Ordering::SeqCst);
+    PENDING.lock().unwrap().insert(id, tx);
+    (id, rx)
+}
+
+/// Resolves a pending request, waking up the task awaiting it. A no-op if
+/// the request already timed out or was resolved.
+pub fn resolve(id: u64, result: Result<serde_json::Value>) {
+    if let Some(tx) = PENDING.lock().unwrap().remove(&id) {
+        let _ = tx.send(result);
+    }
+}
+
+/// Awaits the native completion for `id`, failing with
+/// `Error::PlatformError` if it times out or the sender is dropped (e.g. a
+/// StoreKit/Billing callback that never fires). `on_abandoned` runs in either
+/// of those cases so callers that stashed per-request context in a side
+/// table (e.g. `ios.rs`'s `*_CONTEXT` maps) can evict it instead of leaking
+/// it forever.
+pub async fn wait<F>(
+    id: u64,
+    rx: oneshot::Receiver<Result<serde_json::Value>>,
+    on_abandoned: F,
+) -> Result<serde_json::Value>
+where
+    F: FnOnce(),
+{
+    match tokio::time::timeout(timeout(), rx).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(_)) => {
+            PENDING.lock().unwrap().remove(&id);
+            on_abandoned();
+            Err(Error::PlatformError(
+                "native callback sender was dropped".to_string(),
+            ))
+        }
+        Err(_) => {
+            PENDING.lock().unwrap().remove(&id);
+            on_abandoned();
+            Err(Error::PlatformError("request timed out".to_string()))
+        }
+    }
+}
+
+/// Entry point the Objective-C completion block / Java callback calls back
+/// into once the native platform has a result (or error) for `request_id`.
+///
+/// # Safety
+/// `payload_json` must be a valid, NUL-terminated C string for the duration
+/// of this call, as handed back from the ObjC/JNI boundary.
+#[no_mangle]
+pub unsafe extern "C" fn on_native_result(request_id: u64, success: bool, payload_json: *const c_char) {
+    let result = if payload_json.is_null() {
+        Err(Error::PlatformError(
+            "native call returned no payload".to_string(),
+        ))
+    } else {
+        let json_str = CStr::from_ptr(payload_json).to_string_lossy();
+        if success {
+            serde_json::from_str::<serde_json::Value>(&json_str)
+                .map_err(|e| Error::PlatformError(format!("invalid native payload: {e}")))
+        } else {
+            Err(Error::PlatformError(json_str.into_owned()))
+        }
+    };
+
+    resolve(request_id, result);
+}
@@ -0,0 +1,310 @@
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const APPLE_PRODUCTION_URL: &str = "https://buy.itunes.apple.com/verifyReceipt";
+const APPLE_SANDBOX_URL: &str = "https://sandbox.itunes.apple.com/verifyReceipt";
+const APPLE_SANDBOX_RECEIPT_STATUS: i64 = 21007;
+const GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const GOOGLE_AUTH_SCOPE: &str = "https://www.googleapis.com/auth/androidpublisher";
+
+/// Configuration for server-side receipt validation, supplied via
+/// [`crate::SubscriptionsBuilder`].
+#[derive(Debug, Default, Clone)]
+pub struct ValidationConfig {
+    pub(crate) apple_shared_secret: Option<String>,
+    pub(crate) google_service_account_key: Option<String>,
+    pub(crate) google_package_name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Environment {
+    Production,
+    Sandbox,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ValidationResult {
+    is_valid: bool,
+    is_expired: bool,
+    expiry_ms: Option<u64>,
+    environment: Environment,
+}
+
+pub async fn validate_receipt(
+    config: &ValidationConfig,
+    platform: &str,
+    receipt_data: &str,
+    product_id: &str,
+) -> Result<ValidationResult> {
+    match platform {
+        "ios" => validate_apple_receipt(config, receipt_data, product_id).await,
+        "android" => validate_google_receipt(config, receipt_data, product_id).await,
+        other => Err(Error::ValidationError(format!(
+            "unsupported platform: {other}"
+        ))),
+    }
+}
+
+async fn validate_apple_receipt(
+    config: &ValidationConfig,
+    receipt_data: &str,
+    product_id: &str,
+) -> Result<ValidationResult> {
+    let shared_secret = config.apple_shared_secret.as_ref().ok_or_else(|| {
+        Error::ValidationError("apple_shared_secret was not configured".to_string())
+    })?;
+
+    let (body, environment) = fetch_apple_receipt(APPLE_PRODUCTION_URL, receipt_data, shared_secret)
+        .await?;
+
+    let (body, environment) = if body["status"].as_i64() == Some(APPLE_SANDBOX_RECEIPT_STATUS) {
+        fetch_apple_receipt(APPLE_SANDBOX_URL, receipt_data, shared_secret).await?
+    } else {
+        (body, environment)
+    };
+
+    let status = body["status"].as_i64().unwrap_or(-1);
+    if status != 0 {
+        return Err(Error::ValidationError(format!(
+            "Apple receipt verification failed with status {status}"
+        )));
+    }
+
+    let latest_receipt_info = body["latest_receipt_info"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+
+    let expiry_ms = latest_expiry_ms_for_product(&latest_receipt_info, product_id);
+
+    let is_expired = match expiry_ms {
+        Some(expiry) => expiry <= now_ms(),
+        None => true,
+    };
+
+    Ok(ValidationResult {
+        is_valid: true,
+        is_expired,
+        expiry_ms,
+        environment,
+    })
+}
+
+/// Picks the furthest-out `expires_date_ms` among `latest_receipt_info`
+/// entries for `product_id`.
+///
+/// A single App Store receipt can carry transactions for every subscription
+/// product the account has ever bought, so without filtering by
+/// `product_id` first, the greatest `expires_date_ms` across the whole
+/// receipt could belong to a different product than the one asked about.
+fn latest_expiry_ms_for_product(
+    latest_receipt_info: &[serde_json::Value],
+    product_id: &str,
+) -> Option<u64> {
+    latest_receipt_info
+        .iter()
+        .filter(|entry| entry["product_id"].as_str() == Some(product_id))
+        .max_by_key(|entry| {
+            entry["expires_date_ms"]
+                .as_str()
+                .and_then(|s| s.parse::<i64>().ok())
+                .unwrap_or(0)
+        })
+        .and_then(|entry| entry["expires_date_ms"].as_str())
+        .and_then(|s| s.parse::<u64>().ok())
+}
+
+async fn fetch_apple_receipt(
+    url: &str,
+    receipt_data: &str,
+    shared_secret: &str,
+) -> Result<(serde_json::Value, Environment)> {
+    let environment = if url == APPLE_SANDBOX_URL {
+        Environment::Sandbox
+    } else {
+        Environment::Production
+    };
+
+    let body = serde_json::json!({
+        "receipt-data": receipt_data,
+        "password": shared_secret,
+        "exclude-old-transactions": true,
+    });
+
+    let response = reqwest::Client::new()
+        .post(url)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| Error::ValidationError(format!("failed to reach Apple: {e}")))?
+        .json::<serde_json::Value>()
+        .await
+        .map_err(|e| Error::ValidationError(format!("invalid Apple response: {e}")))?;
+
+    Ok((body_or_response(response), environment))
+}
+
+fn body_or_response(response: serde_json::Value) -> serde_json::Value {
+    response
+}
+
+async fn validate_google_receipt(
+    config: &ValidationConfig,
+    purchase_token: &str,
+    product_id: &str,
+) -> Result<ValidationResult> {
+    let key_json = config.google_service_account_key.as_ref().ok_or_else(|| {
+        Error::ValidationError("google_service_account_key was not configured".to_string())
+    })?;
+
+    let access_token = google_access_token(key_json).await?;
+    let package_name = config.google_package_name.as_ref().ok_or_else(|| {
+        Error::ValidationError("google_package_name was not configured".to_string())
+    })?;
+
+    let url = format!(
+        "https://androidpublisher.googleapis.com/androidpublisher/v3/applications/{package_name}/purchases/subscriptions/{product_id}/tokens/{purchase_token}"
+    );
+
+    let response = reqwest::Client::new()
+        .get(&url)
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .map_err(|e| Error::ValidationError(format!("failed to reach Google Play: {e}")))?;
+
+    let status = response.status();
+    let body = response
+        .json::<serde_json::Value>()
+        .await
+        .map_err(|e| Error::ValidationError(format!("invalid Google Play response: {e}")))?;
+
+    if !status.is_success() {
+        let message = body["error"]["message"]
+            .as_str()
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("Android Publisher API returned {status}"));
+        return Err(Error::ValidationError(format!(
+            "Google Play receipt verification failed: {message}"
+        )));
+    }
+
+    let expiry_ms = body["expiryTimeMillis"]
+        .as_str()
+        .and_then(|s| s.parse::<u64>().ok());
+
+    let is_expired = match expiry_ms {
+        Some(expiry) => expiry <= now_ms(),
+        None => true,
+    };
+
+    Ok(ValidationResult {
+        is_valid: true,
+        is_expired,
+        expiry_ms,
+        environment: Environment::Production,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct ServiceAccountKey {
+    client_email: String,
+    private_key: String,
+}
+
+#[derive(Debug, Serialize)]
+struct GoogleClaims {
+    iss: String,
+    scope: String,
+    aud: String,
+    iat: u64,
+    exp: u64,
+}
+
+async fn google_access_token(key_json: &str) -> Result<String> {
+    let key: ServiceAccountKey = serde_json::from_str(key_json)
+        .map_err(|e| Error::ValidationError(format!("invalid service account key: {e}")))?;
+
+    let iat = now_ms() / 1000;
+    let claims = GoogleClaims {
+        iss: key.client_email,
+        scope: GOOGLE_AUTH_SCOPE.to_string(),
+        aud: GOOGLE_TOKEN_URL.to_string(),
+        iat,
+        exp: iat + 3600,
+    };
+
+    let encoding_key = jsonwebtoken::EncodingKey::from_rsa_pem(key.private_key.as_bytes())
+        .map_err(|e| Error::ValidationError(format!("invalid service account private key: {e}")))?;
+    let jwt = jsonwebtoken::encode(
+        &jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256),
+        &claims,
+        &encoding_key,
+    )
+    .map_err(|e| Error::ValidationError(format!("failed to sign service account JWT: {e}")))?;
+
+    let response = reqwest::Client::new()
+        .post(GOOGLE_TOKEN_URL)
+        .form(&[
+            ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+            ("assertion", jwt.as_str()),
+        ])
+        .send()
+        .await
+        .map_err(|e| Error::ValidationError(format!("failed to reach Google OAuth: {e}")))?
+        .json::<serde_json::Value>()
+        .await
+        .map_err(|e| Error::ValidationError(format!("invalid Google OAuth response: {e}")))?;
+
+    response["access_token"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| Error::ValidationError("Google OAuth response had no access_token".to_string()))
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(product_id: &str, expires_date_ms: &str) -> serde_json::Value {
+        serde_json::json!({
+            "product_id": product_id,
+            "expires_date_ms": expires_date_ms,
+        })
+    }
+
+    #[test]
+    fn picks_the_latest_entry_for_the_requested_product() {
+        let entries = vec![
+            entry("yearly_plan", "4000000000000"),
+            entry("weekly_plan", "1000000000000"),
+            entry("weekly_plan", "2000000000000"),
+        ];
+
+        assert_eq!(
+            latest_expiry_ms_for_product(&entries, "weekly_plan"),
+            Some(2000000000000)
+        );
+    }
+
+    #[test]
+    fn ignores_entries_for_other_products() {
+        let entries = vec![entry("yearly_plan", "9999999999999")];
+
+        assert_eq!(latest_expiry_ms_for_product(&entries, "weekly_plan"), None);
+    }
+
+    #[test]
+    fn returns_none_for_empty_receipt() {
+        assert_eq!(latest_expiry_ms_for_product(&[], "weekly_plan"), None);
+    }
+}